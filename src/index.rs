@@ -0,0 +1,127 @@
+//! Per-backup manifest mapping the source file tree to chunk digests (or
+//! archive membership), plus enough metadata to answer "what's in this
+//! backup" and "did this file change" without re-reading the source tree.
+//!
+//! This is what turns a chunked backup into an incremental one: re-running a
+//! backup re-chunks the source tree, but `upload_chunk` skips any digest
+//! already present under `chunks/` in the bucket, so only chunks introduced
+//! since the last run actually get uploaded. The same manifest also backs
+//! `restore --verify`, since it already has every file's size and digest on
+//! hand. Listing backups (`restore` without `--name`) does not read it.
+
+use crate::crypto::EncryptionMode;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// GCS object prefix under which content-addressed chunks are stored.
+pub const CHUNK_PREFIX: &str = "chunks/";
+
+/// How a backup's file contents are stored at rest, independent of whether
+/// they're also encrypted.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionMethod {
+    /// Stored as-is: content-defined chunks aren't individually compressed.
+    #[default]
+    None,
+    /// Stored inside a Deflate-compressed zip archive (the legacy path).
+    Deflated,
+}
+
+/// One source file's manifest entry: where to find it again, and enough to
+/// verify it came back correctly.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileEntry {
+    /// Path relative to the backed-up directory's root.
+    pub path: PathBuf,
+    /// Size of the file's plaintext contents, in bytes.
+    pub size: u64,
+    /// Last-modified time of the source file, RFC 3339 formatted.
+    pub modified: String,
+    /// Hex-encoded SHA-256 digest of the whole (plaintext) file, for a
+    /// single integrity check that doesn't require re-hashing every chunk.
+    pub digest: String,
+    /// Hex-encoded SHA-256 digests of the file's content-defined chunks, in
+    /// the order they must be concatenated to reconstruct it. Empty for
+    /// files stored in a legacy zip archive instead.
+    #[serde(default)]
+    pub chunks: Vec<String>,
+}
+
+/// The full catalog for one backup run: every file it contains, where its
+/// contents are stored, and summary counts (total files, size and chunks).
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BackupIndex {
+    pub files: Vec<FileEntry>,
+    /// Whether/how the chunks this index points to are encrypted, so
+    /// restore knows what key material it needs before fetching them.
+    #[serde(default)]
+    pub encryption: EncryptionMode,
+    /// How file contents are stored at rest.
+    #[serde(default)]
+    pub compression: CompressionMethod,
+    /// Object names of the archives this backup's files live in, relative
+    /// to the backup's own object prefix. Empty for chunked backups, whose files live
+    /// under `chunks/` instead.
+    #[serde(default)]
+    pub archives: Vec<String>,
+    pub total_files: usize,
+    pub total_size: u64,
+    pub total_chunks: usize,
+}
+
+impl BackupIndex {
+    pub fn new(encryption: EncryptionMode, compression: CompressionMethod) -> Self {
+        Self {
+            files: Vec::new(),
+            encryption,
+            compression,
+            archives: Vec::new(),
+            total_files: 0,
+            total_size: 0,
+            total_chunks: 0,
+        }
+    }
+
+    /// Record one file's manifest entry, keeping the summary counts in sync.
+    pub fn push_file(&mut self, entry: FileEntry) {
+        self.total_files += 1;
+        self.total_size += entry.size;
+        self.total_chunks += entry.chunks.len();
+        self.files.push(entry);
+    }
+
+    /// Record the archive object names this backup's files are stored in.
+    pub fn set_archives(&mut self, archives: Vec<String>) {
+        self.archives = archives;
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize index")?;
+        fs::write(path, json)
+            .with_context(|| format!("Failed to write index file {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Namespace chunk objects by the encryption identity that produced them, so
+/// chunks encrypted under different keys (or not at all) never collide just
+/// because their plaintext happens to hash the same: `chunk_exists_in_gcs`
+/// dedups purely on this object name, and if a passphrase/keyfile run ever
+/// reused a plaintext run's object name, it would skip "already uploaded"
+/// and leave an index pointing at bytes the wrong key can't decrypt.
+fn encryption_namespace(mode: &EncryptionMode) -> String {
+    match mode {
+        EncryptionMode::None => "plain".to_string(),
+        EncryptionMode::Passphrase { salt } => format!("passphrase-{salt}"),
+        EncryptionMode::Keyfile { path } => format!("keyfile-{}", path.display()),
+    }
+}
+
+/// GCS object name for a chunk with the given hex digest, namespaced by the
+/// encryption mode its bytes were (or weren't) encrypted under.
+pub fn chunk_object_name(digest: &str, mode: &EncryptionMode) -> String {
+    format!("{CHUNK_PREFIX}{}/{digest}", encryption_namespace(mode))
+}
@@ -1,23 +1,56 @@
 use anyhow::{Context, Result};
-use chrono::Local;
+use chrono::{Local, TimeZone};
 use clap::Parser;
+use futures::StreamExt;
 use google_cloud_auth::credentials::CredentialsFile;
 use google_cloud_storage::client::{Client, ClientConfig};
+use google_cloud_storage::http::objects::delete::DeleteObjectRequest;
+use google_cloud_storage::http::objects::get::GetObjectRequest;
+use google_cloud_storage::http::objects::list::ListObjectsRequest;
 use google_cloud_storage::http::objects::upload::{Media, UploadObjectRequest, UploadType};
+use google_cloud_storage::http::resumable_upload_client::{ChunkSize, ResumableUploadClient, UploadStatus};
 use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use tokio_stream::wrappers::ReceiverStream;
 use zip::write::FileOptions;
 use zip::ZipWriter;
 
+mod chunker;
+mod crypto;
+mod index;
+mod progress;
+mod restore;
+mod retention;
+mod streaming;
+
+use crypto::{EncryptionContext, EncryptionMode};
+use index::{BackupIndex, CompressionMethod, FileEntry};
+use retention::PruneOptions;
 
-const KEEP_LOCAL_BACKUP_DAYS: i64 = 7;
 const CHUNK_SIZE: i64 = 50;
+/// Name of the per-backup chunk index, uploaded alongside the chunks it
+/// references.
+pub(crate) const INDEX_FILE_NAME: &str = "index.json";
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Backup a directory to Google Cloud Storage", long_about = None)]
-struct Args {
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Back up a directory to GCS (the default behavior of earlier versions)
+    Backup(BackupArgs),
+    /// List or restore backups previously pushed to GCS
+    Restore(restore::RestoreArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct BackupArgs {
     /// Path to the directory to backup
     #[arg(short, long)]
     source: Option<PathBuf>,
@@ -37,6 +70,24 @@ struct Args {
     /// Use config file instead of arguments
     #[arg(long, default_value = "backup-config.json")]
     config: PathBuf,
+
+    /// Print prune keep/remove decisions without deleting anything
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+
+    /// Encrypt chunks with a passphrase-derived key before upload (chunked
+    /// backups only; zip archive backups are not encrypted)
+    #[arg(long, default_value_t = false)]
+    encrypt: bool,
+
+    /// Passphrase used to derive the encryption key (required wherever
+    /// `encrypt` is configured, either now or in the loaded config)
+    #[arg(long, env = "MUSIC_BACKUP_PASSPHRASE")]
+    passphrase: Option<String>,
+
+    /// Path to a raw 32-byte keyfile to use instead of a passphrase
+    #[arg(long)]
+    keyfile: Option<PathBuf>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -45,6 +96,31 @@ struct BackupConfig {
     bucket_name: String,
     credentials_path: PathBuf,
     destination_folder: Option<String>,
+    /// When true, back up via content-defined chunking with server-side
+    /// dedup instead of a monolithic zip archive. Defaults to on for new
+    /// configs; existing configs without this field also default to on.
+    #[serde(default = "default_true")]
+    use_chunked_backup: bool,
+    /// Grandfather-father-son retention policy applied to both local and
+    /// remote backup history.
+    #[serde(default = "default_prune_options")]
+    prune: PruneOptions,
+    /// Client-side encryption mode applied to chunks before upload. Only
+    /// allowed together with `use_chunked_backup`; zip archives are not
+    /// encrypted. Defaults to no encryption.
+    #[serde(default)]
+    encryption: EncryptionMode,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_prune_options() -> PruneOptions {
+    PruneOptions {
+        keep_last: 7,
+        ..Default::default()
+    }
 }
 
 impl BackupConfig {
@@ -66,8 +142,12 @@ impl BackupConfig {
 }
 
 
-fn zip_directory(source_dir: &Path, output_dir: &Path) -> Result<()> {
-    // zips directory in chunks locally
+/// Zip `source_dir` into `output_dir`, splitting the entries across
+/// multiple `<n>.zip` archives of at most `CHUNK_SIZE` files each. Returns
+/// the file count and the archive object names actually written, in the
+/// order they were created, so callers don't have to re-derive that layout
+/// from `CHUNK_SIZE` math after the fact.
+fn zip_directory(source_dir: &Path, output_dir: &Path) -> Result<(usize, Vec<String>)> {
     println!("Creating zip archive...");
     println!("Source: {}", source_dir.display());
     println!("Output directory: {}", output_dir.display());
@@ -77,27 +157,10 @@ fn zip_directory(source_dir: &Path, output_dir: &Path) -> Result<()> {
         .unix_permissions(0o755);
 
     let mut file_count = 0;
-    
-    // Walk through the directory
-    let walkdir = walkdir::WalkDir::new(source_dir);
-
-    // init a dud zip file (I'm sure this can be done better lol)
-    let mut zip = ZipWriter::new(Path::new("temp.zip"));
-    for entry in walkdir.into_iter().filter_map(|e| e.ok()) {
-        // get ZIP chunk filename and init if necessary
-        if file_count % CHUNK_SIZE == 0 && file_count == 0 {
-            let zip_file_name = if file_count == 0 {
-                "0.zip"
-            } else {
-                // close precious zip file before we init the next one
-                zip.finish().context("Failed to finalize zip file")?;
-                &format!("{}.zip", file_count / CHUNK_SIZE)
-            };
-            let mut file = File::create(output_dir.join(zip_file_name)).context("Failed to create zip file")?;
-            let mut zip = ZipWriter::new(file);
-        }
-        
-        // now read the file and 
+    let mut archive_names: Vec<String> = Vec::new();
+    let mut current: Option<ZipWriter<File>> = None;
+
+    for entry in walkdir::WalkDir::new(source_dir).into_iter().filter_map(|e| e.ok()) {
         let path = entry.path();
         let name = path.strip_prefix(source_dir)
             .context("Failed to strip prefix")?;
@@ -108,6 +171,18 @@ fn zip_directory(source_dir: &Path, output_dir: &Path) -> Result<()> {
         }
 
         if path.is_file() {
+            if current.is_none() || file_count % CHUNK_SIZE as usize == 0 {
+                if let Some(mut writer) = current.take() {
+                    writer.finish().context("Failed to finalize zip file")?;
+                }
+                let archive_name = format!("{}.zip", archive_names.len());
+                let file = File::create(output_dir.join(&archive_name))
+                    .context("Failed to create zip file")?;
+                current = Some(ZipWriter::new(file));
+                archive_names.push(archive_name);
+            }
+            let writer = current.as_mut().expect("just opened above");
+
             // read file data into buffer
             let mut f = File::open(path)
                 .context("Failed to open file for zipping")?;
@@ -115,104 +190,615 @@ fn zip_directory(source_dir: &Path, output_dir: &Path) -> Result<()> {
             f.read_to_end(&mut buffer)
                 .context("Failed to read file")?;
 
-            // open zip file to write new entry
-            zip.start_file(name.to_string_lossy().into_owned(), options)
+            writer.start_file(name.to_string_lossy().into_owned(), options)
                 .context("Failed to start zip file entry")?;
-            zip.write_all(&buffer)
+            writer.write_all(&buffer)
                 .context("Failed to write to zip")?;
-            
+
             file_count += 1;
-        } else if !name.as_os_str().is_empty() {
-            zip.add_directory(name.to_string_lossy().into_owned(), options)
+        } else if let Some(writer) = current.as_mut() {
+            writer.add_directory(name.to_string_lossy().into_owned(), options)
                 .context("Failed to add directory to zip")?;
         }
     }
 
-    
+    if let Some(mut writer) = current.take() {
+        writer.finish().context("Failed to finalize zip file")?;
+    }
+
     println!("Zip created successfully!");
-    println!("Files: {}", file_count);
-    
+    println!("Files: {file_count}, archives: {}", archive_names.len());
+
+    Ok((file_count, archive_names))
+}
+
+/// Walk `source_dir` collecting each file's manifest entry (size, mtime,
+/// whole-file digest) without chunking it, for the legacy zip backup path.
+fn build_manifest(source_dir: &Path) -> Result<Vec<FileEntry>> {
+    let mut entries = Vec::new();
+
+    for entry in walkdir::WalkDir::new(source_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let relative = path
+            .strip_prefix(source_dir)
+            .context("Failed to strip prefix")?
+            .to_path_buf();
+
+        let metadata = fs::metadata(path)
+            .with_context(|| format!("Failed to read metadata for {}", path.display()))?;
+        let modified = metadata
+            .modified()
+            .with_context(|| format!("Failed to read mtime for {}", path.display()))?;
+        let digest = chunker::file_digest(path)
+            .with_context(|| format!("Failed to hash {}", path.display()))?;
+
+        entries.push(FileEntry {
+            path: relative,
+            size: metadata.len(),
+            modified: chrono::DateTime::<Local>::from(modified).to_rfc3339(),
+            digest,
+            chunks: Vec::new(),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Upload a saved `BackupIndex` at `local_index_path` to `{gcs_dir}/index.json`.
+async fn upload_index_file(
+    client: &Client,
+    local_index_path: &Path,
+    bucket_name: &str,
+    gcs_dir: &str,
+) -> Result<()> {
+    let index_object_name = format!("{}/{}", gcs_dir, INDEX_FILE_NAME);
+    let index_bytes = fs::read(local_index_path).context("Failed to read local index file")?;
+    let media = Media::new(index_object_name.clone());
+    let upload_type = UploadType::Simple(media);
+    client
+        .upload_object(
+            &UploadObjectRequest {
+                bucket: bucket_name.to_string(),
+                ..Default::default()
+            },
+            index_bytes,
+            &upload_type,
+        )
+        .await
+        .context("Failed to upload backup index")?;
     Ok(())
 }
 
+/// Maximum number of upload attempts before giving up.
+const MAX_UPLOAD_ATTEMPTS: u32 = 5;
+
+/// Upload `file_path` to `bucket_name`/`destination_name` using GCS's
+/// resumable-upload protocol instead of a single `UploadType::Simple`
+/// request, so a transient failure partway through a multi-gigabyte archive
+/// resumes from the byte the server actually committed instead of
+/// re-streaming the whole file. One session is opened for the whole upload;
+/// on a failed attempt we ask the server (not our own byte counter, in case
+/// the failure happened mid-chunk) how much of the session it has, and
+/// stream only the remainder on the next attempt.
 async fn upload_to_gcs(
     file_path: &Path,
     bucket_name: &str,
     destination_name: String,
     credentials_path: &Path,
 ) -> Result<()> {
-    // Read credentials
+    let client = build_gcs_client(credentials_path).await?;
+
+    let media = Media::new(destination_name.clone());
+    let uploader = client
+        .prepare_resumable_upload(
+            &UploadObjectRequest {
+                bucket: bucket_name.to_string(),
+                ..Default::default()
+            },
+            &UploadType::Simple(media),
+        )
+        .await
+        .context("Failed to start resumable upload session")?;
+
+    let mut offset = 0u64;
+    for attempt in 1..=MAX_UPLOAD_ATTEMPTS {
+        let (mut stream, total_len) = streaming::stream_file(file_path, offset).await?;
+        let progress = std::sync::Arc::new(std::sync::Mutex::new(progress::UploadProgress::new(
+            total_len, offset,
+        )));
+
+        match upload_from_stream(&uploader, &mut stream, offset, total_len, &progress).await {
+            Ok(()) => {
+                progress.lock().unwrap().finish();
+                println!("Upload successful!");
+                println!("Location: gs://{bucket_name}/{destination_name}");
+                return Ok(());
+            }
+            Err(err) if attempt < MAX_UPLOAD_ATTEMPTS => {
+                offset = match uploader.status(Some(total_len)).await {
+                    Ok(UploadStatus::ResumeIncomplete(range)) => range.last_byte + 1,
+                    Ok(UploadStatus::Ok(_)) => return Ok(()),
+                    Ok(UploadStatus::NotStarted) | Err(_) => 0,
+                };
+                println!(
+                    "\nUpload attempt {attempt} failed ({err}); resuming from byte {offset} of {total_len}"
+                );
+            }
+            Err(err) => return Err(err).context("Failed to upload to GCS after retries"),
+        }
+    }
+
+    unreachable!("loop always returns on its last attempt")
+}
+
+/// Feed `stream`'s chunks (each already sized to a multiple of 256 KiB,
+/// except possibly the last) into `uploader`'s existing resumable session,
+/// starting at `start_offset`, reporting progress as each chunk commits.
+async fn upload_from_stream(
+    uploader: &ResumableUploadClient,
+    stream: &mut ReceiverStream<std::io::Result<bytes::Bytes>>,
+    start_offset: u64,
+    total_len: u64,
+    progress: &std::sync::Arc<std::sync::Mutex<progress::UploadProgress>>,
+) -> Result<()> {
+    let mut pos = start_offset;
+
+    while let Some(item) = stream.next().await {
+        let data = item.context("Failed to read file for upload")?;
+        let last_byte = pos + data.len() as u64 - 1;
+        let chunk_size = ChunkSize::new(pos, last_byte, Some(total_len));
+
+        let status = uploader
+            .upload_multiple_chunk(data.clone(), &chunk_size)
+            .await
+            .context("Failed to upload chunk")?;
+
+        progress.lock().unwrap().advance(data.len() as u64);
+        pos += data.len() as u64;
+
+        match status {
+            UploadStatus::Ok(_) => return Ok(()),
+            UploadStatus::ResumeIncomplete(_) => continue,
+            UploadStatus::NotStarted => {
+                anyhow::bail!("Resumable session reported not started partway through the upload")
+            }
+        }
+    }
+
+    if pos == total_len {
+        Ok(())
+    } else {
+        anyhow::bail!("Upload stream ended early at byte {pos} of {total_len}")
+    }
+}
+
+/// Build a GCS client from a service-account credentials file. Shared by
+/// every function that talks to the bucket directly.
+pub(crate) async fn build_gcs_client(credentials_path: &Path) -> Result<Client> {
     let creds_content = fs::read_to_string(credentials_path)
         .context("Failed to read credentials file")?;
-    let creds: CredentialsFile = serde_json::from_str(&creds_content)
-        .context("Failed to parse credentials")?;
-
-    // Create GCS client
+    let creds: CredentialsFile =
+        serde_json::from_str(&creds_content).context("Failed to parse credentials")?;
     let config = ClientConfig::default()
         .with_credentials(creds)
         .await
         .expect("Failed to create client config");
-    let client = Client::new(config);
+    Ok(Client::new(config))
+}
 
-    // Read file
-    let mut file = File::open(file_path)
-        .context("Failed to open file for upload")?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)
-        .context("Failed to read file")?;
+/// Returns true if `object_name` already exists in `bucket_name`, so the
+/// caller can skip re-uploading a chunk that's already stored.
+pub(crate) async fn chunk_exists_in_gcs(client: &Client, bucket_name: &str, object_name: &str) -> Result<bool> {
+    let result = client
+        .get_object(&GetObjectRequest {
+            bucket: bucket_name.to_string(),
+            object: object_name.to_string(),
+            ..Default::default()
+        })
+        .await;
+
+    match result {
+        Ok(_) => Ok(true),
+        Err(err) if err.to_string().contains("404") => Ok(false),
+        Err(err) => Err(err).context("Failed to check whether chunk exists in GCS"),
+    }
+}
 
-    // Upload to bucket
-    let media = Media::new(destination_name.clone());
+/// Upload a single content-addressed chunk under `chunks/<namespace>/<digest>`,
+/// unless it's already present in the bucket, returning whether it was newly
+/// uploaded. The digest is always taken over the plaintext, but the object
+/// name is also namespaced by `encryption_mode` so dedup never mistakes a
+/// chunk encrypted under one key (or not at all) for one encrypted under
+/// another that happens to hash the same plaintext.
+///
+/// Owns the exists-then-put check itself so callers only pay for one
+/// existence check per chunk instead of checking again before calling in.
+async fn upload_chunk(
+    client: &Client,
+    bucket_name: &str,
+    chunk: &chunker::Chunk,
+    encryption: Option<&EncryptionContext>,
+    encryption_mode: &EncryptionMode,
+) -> Result<bool> {
+    let object_name = index::chunk_object_name(&chunk.digest, encryption_mode);
+
+    if chunk_exists_in_gcs(client, bucket_name, &object_name).await? {
+        return Ok(false);
+    }
+
+    let body = match encryption {
+        Some(ctx) => ctx
+            .encrypt(&chunk.data)
+            .with_context(|| format!("Failed to encrypt chunk {}", chunk.digest))?,
+        None => chunk.data.clone(),
+    };
+
+    let media = Media::new(object_name.clone());
     let upload_type = UploadType::Simple(media);
-    let uploaded = client
+    client
         .upload_object(
             &UploadObjectRequest {
                 bucket: bucket_name.to_string(),
                 ..Default::default()
             },
-            buffer,
+            body,
             &upload_type,
         )
         .await
-        .context("Failed to upload to GCS")?;
+        .with_context(|| format!("Failed to upload chunk {}", object_name))?;
+
+    Ok(true)
+}
+
+/// Back up `source_dir` using content-defined chunking: every file is split
+/// into chunks, each chunk is uploaded to `chunks/<namespace>/<sha256>`
+/// unless it's already there, and the resulting file-to-chunks mapping is
+/// saved to `local_index_path` and uploaded to `gcs_dir`/index.json. Re-running this
+/// against a mostly-unchanged tree only uploads the chunks that changed.
+async fn chunked_backup(
+    source_dir: &Path,
+    local_index_path: &Path,
+    bucket_name: &str,
+    gcs_dir: &str,
+    credentials_path: &Path,
+    encryption: Option<&EncryptionContext>,
+    encryption_mode: EncryptionMode,
+) -> Result<()> {
+    println!("Creating chunked backup...");
+    println!("Source: {}", source_dir.display());
+
+    let client = build_gcs_client(credentials_path).await?;
+
+    let mut index = BackupIndex::new(encryption_mode, CompressionMethod::None);
+    let mut uploaded_count = 0;
+
+    for entry in walkdir::WalkDir::new(source_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let relative = path
+            .strip_prefix(source_dir)
+            .context("Failed to strip prefix")?
+            .to_path_buf();
+
+        let metadata = fs::metadata(path)
+            .with_context(|| format!("Failed to read metadata for {}", path.display()))?;
+        let modified = metadata
+            .modified()
+            .with_context(|| format!("Failed to read mtime for {}", path.display()))?;
+
+        let (chunks, digest) = chunker::chunk_file(path)
+            .with_context(|| format!("Failed to chunk {}", path.display()))?;
+        let mut digests = Vec::with_capacity(chunks.len());
+        for chunk in &chunks {
+            if upload_chunk(&client, bucket_name, chunk, encryption, &index.encryption).await? {
+                uploaded_count += 1;
+            }
+            digests.push(chunk.digest.clone());
+        }
+
+        index.push_file(FileEntry {
+            path: relative,
+            size: metadata.len(),
+            modified: chrono::DateTime::<Local>::from(modified).to_rfc3339(),
+            digest,
+            chunks: digests,
+        });
+    }
+
+    index.save(local_index_path)?;
+    upload_index_file(&client, local_index_path, bucket_name, gcs_dir).await?;
+
+    println!("Chunked backup complete!");
+    println!(
+        "Files: {}, chunks: {}, newly uploaded: {uploaded_count}",
+        index.total_files, index.total_chunks
+    );
 
-    println!("Upload successful!");
-    println!("Location: gs://{}/{}", bucket_name, uploaded.name);
-    
     Ok(())
 }
 
-fn cleanup_old_backups(backup_dir: &Path, keep_days: i64) -> Result<()> {
-    let cutoff = Local::now() - chrono::Duration::days(keep_days);
-    
+/// Parse the `<name>-<YYYYMMDD>` timestamp suffix `zip_directory`/
+/// `chunked_backup` name backups with, so pruning can bucket backups by
+/// date without relying on filesystem or object metadata.
+pub(crate) fn parse_backup_timestamp(backup_name: &str) -> Option<chrono::DateTime<Local>> {
+    let date_str = backup_name.rsplit('-').next()?;
+    let date = chrono::NaiveDate::parse_from_str(date_str, "%Y%m%d").ok()?;
+    let naive = date.and_hms_opt(0, 0, 0)?;
+    Local.from_local_datetime(&naive).single()
+}
+
+/// Enumerate the backups present in the local `tmp/` directory: one per
+/// zip-mode subdirectory or chunked-mode `<name>-index.json` file.
+fn local_backups(backup_dir: &Path) -> Result<Vec<retention::Backup>> {
+    let mut by_name = std::collections::HashMap::new();
+
     if !backup_dir.exists() {
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     for entry in fs::read_dir(backup_dir)? {
         let entry = entry?;
         let path = entry.path();
-        
-        if path.extension().and_then(|s| s.to_str()) == Some("zip") {
-            let metadata = fs::metadata(&path)?;
-            if let Ok(modified) = metadata.modified() {
-                let modified_time = chrono::DateTime::<Local>::from(modified);
-                if modified_time < cutoff {
-                    fs::remove_file(&path)?;
-                    println!("Removed old backup: {}", path.file_name().unwrap().to_string_lossy());
-                }
+        let file_name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(n) => n,
+            None => continue,
+        };
+
+        let backup_name = if let Some(stripped) =
+            file_name.strip_suffix(&format!("-{INDEX_FILE_NAME}"))
+        {
+            stripped.to_string()
+        } else if path.is_dir() {
+            file_name.to_string()
+        } else {
+            continue;
+        };
+
+        if let Some(timestamp) = parse_backup_timestamp(&backup_name) {
+            by_name.insert(backup_name.clone(), retention::Backup { name: backup_name, timestamp });
+        }
+    }
+
+    Ok(by_name.into_values().collect())
+}
+
+/// Delete the local files/directories for every backup `plan` marked for
+/// removal, or just report what would be removed when `dry_run` is set.
+fn prune_local_backups(backup_dir: &Path, decisions: &[retention::Decision], dry_run: bool) -> Result<()> {
+    for decision in decisions {
+        if decision.keep {
+            println!("Keeping local backup: {}", decision.backup.name);
+            continue;
+        }
+
+        let verb = if dry_run { "Would remove" } else { "Removing" };
+        println!("{verb} local backup: {}", decision.backup.name);
+        if dry_run {
+            continue;
+        }
+
+        let dir_path = backup_dir.join(&decision.backup.name);
+        if dir_path.is_dir() {
+            fs::remove_dir_all(&dir_path)
+                .with_context(|| format!("Failed to remove {}", dir_path.display()))?;
+        }
+        let index_path = backup_dir.join(format!("{}-{INDEX_FILE_NAME}", decision.backup.name));
+        if index_path.is_file() {
+            fs::remove_file(&index_path)
+                .with_context(|| format!("Failed to remove {}", index_path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Derive the backup name a remote object belongs to, i.e. the first path
+/// segment after `destination_prefix`.
+pub(crate) fn remote_backup_name(object_name: &str, destination_prefix: Option<&str>) -> Option<String> {
+    let rest = match destination_prefix {
+        Some(prefix) => object_name.strip_prefix(&format!("{prefix}/"))?,
+        None => object_name,
+    };
+    let first_segment = rest.split('/').next()?;
+    if first_segment.is_empty() {
+        None
+    } else {
+        Some(first_segment.to_string())
+    }
+}
+
+/// Enumerate the backups present in the bucket under `destination_prefix`,
+/// one per distinct top-level object prefix.
+pub(crate) async fn remote_backups(
+    client: &Client,
+    bucket_name: &str,
+    destination_prefix: Option<&str>,
+) -> Result<Vec<retention::Backup>> {
+    let objects = client
+        .list_objects(&ListObjectsRequest {
+            bucket: bucket_name.to_string(),
+            prefix: destination_prefix.map(|p| format!("{p}/")),
+            ..Default::default()
+        })
+        .await
+        .context("Failed to list objects in bucket")?;
+
+    let mut by_name = std::collections::HashMap::new();
+    for object in objects.items.unwrap_or_default() {
+        if let Some(name) = remote_backup_name(&object.name, destination_prefix) {
+            if let Some(timestamp) = parse_backup_timestamp(&name) {
+                by_name.insert(name.clone(), retention::Backup { name, timestamp });
             }
         }
     }
-    
+
+    Ok(by_name.into_values().collect())
+}
+
+/// Delete every object belonging to a backup `plan` marked for removal, or
+/// just report what would be removed when `dry_run` is set.
+async fn prune_remote_backups(
+    client: &Client,
+    bucket_name: &str,
+    destination_prefix: Option<&str>,
+    decisions: &[retention::Decision],
+    dry_run: bool,
+) -> Result<()> {
+    for decision in decisions {
+        if decision.keep {
+            println!("Keeping remote backup: {}", decision.backup.name);
+            continue;
+        }
+
+        let verb = if dry_run { "Would remove" } else { "Removing" };
+        println!("{verb} remote backup: {}", decision.backup.name);
+        if dry_run {
+            continue;
+        }
+
+        let object_prefix = match destination_prefix {
+            Some(prefix) => format!("{prefix}/{}/", decision.backup.name),
+            None => format!("{}/", decision.backup.name),
+        };
+        let objects = client
+            .list_objects(&ListObjectsRequest {
+                bucket: bucket_name.to_string(),
+                prefix: Some(object_prefix),
+                ..Default::default()
+            })
+            .await
+            .context("Failed to list objects for remote backup to delete")?;
+
+        for object in objects.items.unwrap_or_default() {
+            client
+                .delete_object(&DeleteObjectRequest {
+                    bucket: bucket_name.to_string(),
+                    object: object.name.clone(),
+                    ..Default::default()
+                })
+                .await
+                .with_context(|| format!("Failed to delete {}", object.name))?;
+        }
+    }
     Ok(())
 }
 
+/// Collect the set of chunk digests still referenced by every backup
+/// `decisions` keeps, by downloading and parsing each kept backup's
+/// `index.json`. Backups with no index (legacy zip backups with no
+/// manifest) or no chunks (zip backups with one) simply contribute nothing.
+async fn referenced_chunks(
+    client: &Client,
+    bucket_name: &str,
+    destination_prefix: Option<&str>,
+    decisions: &[retention::Decision],
+) -> Result<std::collections::HashSet<String>> {
+    let mut referenced = std::collections::HashSet::new();
+
+    for decision in decisions {
+        if !decision.keep {
+            continue;
+        }
+
+        let object_prefix = match destination_prefix {
+            Some(prefix) => format!("{prefix}/{}/", decision.backup.name),
+            None => format!("{}/", decision.backup.name),
+        };
+        let index_object = format!("{object_prefix}{INDEX_FILE_NAME}");
+
+        let index_bytes = match restore::download_object(client, bucket_name, &index_object).await {
+            Ok(bytes) => bytes,
+            Err(_) => continue,
+        };
+        let index: BackupIndex = match serde_json::from_slice(&index_bytes) {
+            Ok(index) => index,
+            Err(_) => continue,
+        };
+
+        for file in index.files {
+            referenced.extend(
+                file.chunks
+                    .iter()
+                    .map(|digest| index::chunk_object_name(digest, &index.encryption)),
+            );
+        }
+    }
+
+    Ok(referenced)
+}
+
+/// Delete every object under `chunks/` that no surviving backup's index
+/// references any more, so pruning a chunked backup's index doesn't leave
+/// its now-unreferenced chunks in the bucket forever. Chunks still
+/// referenced by at least one kept backup (the common case, since chunks
+/// are deduplicated across backups under the same encryption identity) are
+/// left alone. `referenced` holds full object names (namespace and all), not
+/// bare digests, since the same digest can legitimately exist more than once
+/// under `chunks/` if it was backed up under more than one encryption mode.
+async fn gc_unreferenced_chunks(
+    client: &Client,
+    bucket_name: &str,
+    referenced: &std::collections::HashSet<String>,
+    dry_run: bool,
+) -> Result<()> {
+    let objects = client
+        .list_objects(&ListObjectsRequest {
+            bucket: bucket_name.to_string(),
+            prefix: Some(index::CHUNK_PREFIX.to_string()),
+            ..Default::default()
+        })
+        .await
+        .context("Failed to list chunks for garbage collection")?;
+
+    let mut removed = 0;
+    for object in objects.items.unwrap_or_default() {
+        if referenced.contains(&object.name) {
+            continue;
+        }
+
+        let verb = if dry_run { "Would remove" } else { "Removing" };
+        println!("{verb} unreferenced chunk: {}", object.name);
+        removed += 1;
+        if dry_run {
+            continue;
+        }
+
+        client
+            .delete_object(&DeleteObjectRequest {
+                bucket: bucket_name.to_string(),
+                object: object.name.clone(),
+                ..Default::default()
+            })
+            .await
+            .with_context(|| format!("Failed to delete {}", object.name))?;
+    }
+
+    println!("Chunk garbage collection: {removed} unreferenced chunk(s) {}", if dry_run { "would be removed" } else { "removed" });
+    Ok(())
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
-    println!("Starting backup..."); 
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Backup(args) => run_backup(args).await,
+        Command::Restore(args) => restore::run(args).await,
+    }
+}
+
+async fn run_backup(args: BackupArgs) -> Result<()> {
+    println!("Starting backup...");
 
     // Load or create config
     let config = if args.config.exists() && args.source.is_none() {
@@ -224,11 +810,22 @@ async fn main() -> Result<()> {
         let bucket = args.bucket.context("Bucket name required")?;
         let credentials = args.credentials.context("Credentials path required")?;
         
+        let encryption = if let Some(path) = &args.keyfile {
+            EncryptionMode::Keyfile { path: path.clone() }
+        } else if args.encrypt {
+            EncryptionMode::Passphrase { salt: crypto::new_salt() }
+        } else {
+            EncryptionMode::None
+        };
+
         let config = BackupConfig {
             source_path: source,
             bucket_name: bucket,
             credentials_path: credentials,
             destination_folder: args.destination_folder,
+            use_chunked_backup: true,
+            prune: default_prune_options(),
+            encryption,
         };
         
         // Save config for future use
@@ -245,6 +842,23 @@ async fn main() -> Result<()> {
     if !config.credentials_path.exists() {
         anyhow::bail!("Credentials file does not exist: {}", config.credentials_path.display());
     }
+    if !config.use_chunked_backup && config.encryption != EncryptionMode::None {
+        anyhow::bail!(
+            "Encryption is only supported for chunked backups; set use_chunked_backup to true, \
+             or disable encryption for this config"
+        );
+    }
+
+    let encryption_ctx = match &config.encryption {
+        EncryptionMode::None => None,
+        EncryptionMode::Passphrase { salt } => {
+            let passphrase = args.passphrase.as_deref().context(
+                "This backup is configured for passphrase encryption; pass --passphrase or set MUSIC_BACKUP_PASSPHRASE",
+            )?;
+            Some(EncryptionContext::from_passphrase(passphrase, salt)?)
+        }
+        EncryptionMode::Keyfile { path } => Some(EncryptionContext::from_keyfile(path)?),
+    };
 
     // Create temp directory for zips
     let temp_dir = Path::new("tmp");
@@ -270,19 +884,100 @@ async fn main() -> Result<()> {
     println!("Bucket: {}", config.bucket_name);
     println!("Destination: {}\n", gcs_dir);
 
-    // ZIP the file to a temp directory
-    zip_directory(&config.source_path, &local_zip_dir)?;
+    if config.use_chunked_backup {
+        let local_index_path = temp_dir.join(format!("{}-{}", zip_name, INDEX_FILE_NAME));
+        chunked_backup(
+            &config.source_path,
+            &local_index_path,
+            &config.bucket_name,
+            &gcs_dir,
+            &config.credentials_path,
+            encryption_ctx.as_ref(),
+            config.encryption.clone(),
+        )
+        .await?;
+    } else {
+        // ZIP the file to a temp directory. zip_directory creates each
+        // <n>.zip archive inside output_dir, so that directory must exist
+        // first; unlike temp_dir above, nothing else creates it for us.
+        fs::create_dir_all(&local_zip_dir)
+            .with_context(|| format!("Failed to create {}", local_zip_dir.display()))?;
+        let (_file_count, archives) = zip_directory(&config.source_path, &local_zip_dir)?;
+
+        // Upload each archive as its own object, named to match what the
+        // manifest's `archives` list (and restore_from_archives) expect to
+        // find at `{gcs_dir}/<n>.zip` — uploading local_zip_dir itself would
+        // hand a directory to upload_to_gcs, which can only stream a file.
+        for archive_name in &archives {
+            upload_to_gcs(
+                &local_zip_dir.join(archive_name),
+                &config.bucket_name,
+                format!("{gcs_dir}/{archive_name}"),
+                &config.credentials_path,
+            )
+            .await?;
+        }
+
+        // Build and upload a manifest alongside the archive(s) so restore
+        // can list/verify this backup's contents the same way it does for
+        // chunked ones.
+        let mut index = BackupIndex::new(config.encryption.clone(), CompressionMethod::Deflated);
+        for entry in build_manifest(&config.source_path)? {
+            index.push_file(entry);
+        }
+        index.set_archives(archives);
+
+        let local_index_path = temp_dir.join(format!("{}-{}", zip_name, INDEX_FILE_NAME));
+        index.save(&local_index_path)?;
+        let client = build_gcs_client(&config.credentials_path).await?;
+        upload_index_file(&client, &local_index_path, &config.bucket_name, &gcs_dir).await?;
+    }
 
-    // Then upload to GCS
-    upload_to_gcs(
-        &local_zip_dir
+    // Prune local and remote backup history to the configured GFS policy.
+    // This deletes backups from the bucket, not just the local tmp/ dir, on
+    // every run by default (keep_last defaults to 7); pass --dry-run first
+    // if you haven't checked what a config's policy keeps.
+    println!(
+        "\nApplying retention policy (keep_last={}, keep_daily={}, keep_weekly={}, keep_monthly={}, keep_yearly={}){}...",
+        config.prune.keep_last,
+        config.prune.keep_daily,
+        config.prune.keep_weekly,
+        config.prune.keep_monthly,
+        config.prune.keep_yearly,
+        if args.dry_run { " [dry run]" } else { "" },
+    );
+    let local_plan = retention::plan(&local_backups(temp_dir)?, &config.prune);
+    prune_local_backups(temp_dir, &local_plan, args.dry_run)?;
+
+    let client = build_gcs_client(&config.credentials_path).await?;
+    let remote_plan = retention::plan(
+        &remote_backups(&client, &config.bucket_name, config.destination_folder.as_deref()).await?,
+        &config.prune,
+    );
+    prune_remote_backups(
+        &client,
         &config.bucket_name,
-        &gcs_dir,
-        &config.credentials_path,
-    ).await?;
+        config.destination_folder.as_deref(),
+        &remote_plan,
+        args.dry_run,
+    )
+    .await?;
+
+    if config.use_chunked_backup {
+        // Chunks live under the bucket-global chunks/ prefix, not under a
+        // backup's own object prefix, so pruning a backup's index above
+        // doesn't reclaim the chunks it was the last reference to. Sweep
+        // for and remove any chunk no surviving backup's index references.
+        let referenced = referenced_chunks(
+            &client,
+            &config.bucket_name,
+            config.destination_folder.as_deref(),
+            &remote_plan,
+        )
+        .await?;
+        gc_unreferenced_chunks(&client, &config.bucket_name, &referenced, args.dry_run).await?;
+    }
 
-    // and cleanup the temp directory
-    cleanup_old_backups(temp_dir, KEEP_LOCAL_BACKUP_DAYS)?;
     println!("Backup completed successfully!");
 
     Ok(())
@@ -0,0 +1,69 @@
+//! Upload progress reporting: bytes/sec and ETA printed to stdout.
+
+use std::time::Instant;
+
+pub struct UploadProgress {
+    total_bytes: u64,
+    sent_bytes: u64,
+    resumed_from: u64,
+    started_at: Instant,
+}
+
+impl UploadProgress {
+    /// Start tracking progress for an upload of `total_bytes`, `resumed_from`
+    /// of which the server has already committed (0 for a fresh upload).
+    pub fn new(total_bytes: u64, resumed_from: u64) -> Self {
+        Self {
+            total_bytes,
+            sent_bytes: resumed_from,
+            resumed_from,
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Record that `delta` more bytes were sent and print an updated
+    /// progress line.
+    pub fn advance(&mut self, delta: u64) {
+        self.sent_bytes += delta;
+
+        let elapsed = self.started_at.elapsed().as_secs_f64().max(0.001);
+        let rate_bytes_per_sec = (self.sent_bytes - self.resumed_from) as f64 / elapsed;
+        let remaining_bytes = self.total_bytes.saturating_sub(self.sent_bytes) as f64;
+        let eta_secs = if rate_bytes_per_sec > 0.0 {
+            remaining_bytes / rate_bytes_per_sec
+        } else {
+            0.0
+        };
+
+        print!(
+            "\r  {:.1} / {:.1} MiB uploaded ({:.1} MiB/s, ETA {})",
+            mib(self.sent_bytes),
+            mib(self.total_bytes),
+            mib(rate_bytes_per_sec as u64),
+            format_duration(eta_secs),
+        );
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+    }
+
+    pub fn finish(&self) {
+        println!();
+    }
+}
+
+fn mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn format_duration(seconds: f64) -> String {
+    if !seconds.is_finite() || seconds <= 0.0 {
+        return "0s".to_string();
+    }
+    let total_secs = seconds.round() as u64;
+    let mins = total_secs / 60;
+    let secs = total_secs % 60;
+    if mins > 0 {
+        format!("{mins}m{secs:02}s")
+    } else {
+        format!("{secs}s")
+    }
+}
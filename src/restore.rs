@@ -0,0 +1,361 @@
+//! `restore` subcommand: list backups in a bucket, or pull one back down.
+//!
+//! Chunked backups are restored by fetching the backup's `index.json`,
+//! downloading every chunk it references (decrypting first if the index
+//! says to), verifying each chunk's SHA-256 against the digest its object
+//! is named after, and concatenating chunks back into files; the whole-file
+//! digest in the manifest is then checked against the reconstructed bytes.
+//! Zip-archive backups with a manifest are restored by downloading the
+//! archives it lists and checking each extracted file's size/digest against
+//! the manifest. Older zip backups with no manifest fall back to
+//! downloading and extracting every `.zip` object under the backup's
+//! prefix, with no integrity check available. `--verify` does the
+//! download-and-check step without writing anything to `target`.
+
+use crate::crypto::{EncryptionContext, EncryptionMode};
+use crate::index::{chunk_object_name, BackupIndex};
+use crate::{build_gcs_client, remote_backups};
+use anyhow::{Context, Result};
+use clap::Args;
+use google_cloud_storage::client::Client;
+use google_cloud_storage::http::objects::download::Range;
+use google_cloud_storage::http::objects::get::GetObjectRequest;
+use google_cloud_storage::http::objects::list::ListObjectsRequest;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct RestoreArgs {
+    /// GCS bucket name
+    #[arg(short, long)]
+    bucket: String,
+
+    /// Path to GCS credentials JSON file
+    #[arg(short, long)]
+    credentials: PathBuf,
+
+    /// Destination folder the backup was pushed under (optional; must
+    /// match what `backup` used)
+    #[arg(short, long)]
+    destination_folder: Option<String>,
+
+    /// Name of the backup to restore (e.g. `MusicLibrary-20260725`). When
+    /// omitted, lists the backups available in the bucket instead.
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Local directory to restore files into (required unless --verify)
+    #[arg(long)]
+    target: Option<PathBuf>,
+
+    /// Check chunk integrity without writing any files
+    #[arg(long, default_value_t = false)]
+    verify: bool,
+
+    /// Passphrase to decrypt the backup, if it was encrypted with one
+    #[arg(long, env = "MUSIC_BACKUP_PASSPHRASE")]
+    passphrase: Option<String>,
+
+    /// Keyfile to decrypt the backup, if it was encrypted with one
+    #[arg(long)]
+    keyfile: Option<PathBuf>,
+}
+
+pub async fn run(args: RestoreArgs) -> Result<()> {
+    let client = build_gcs_client(&args.credentials).await?;
+
+    let backup_name = match &args.name {
+        Some(name) => name.clone(),
+        None => {
+            list_backups(&client, &args.bucket, args.destination_folder.as_deref()).await?;
+            return Ok(());
+        }
+    };
+
+    if !args.verify && args.target.is_none() {
+        anyhow::bail!("--target <dir> is required unless --verify is set");
+    }
+
+    let object_prefix = match &args.destination_folder {
+        Some(folder) => format!("{folder}/{backup_name}/"),
+        None => format!("{backup_name}/"),
+    };
+
+    match download_object(&client, &args.bucket, &format!("{object_prefix}{}", crate::INDEX_FILE_NAME)).await {
+        Ok(index_bytes) => {
+            let index: BackupIndex =
+                serde_json::from_slice(&index_bytes).context("Failed to parse backup index")?;
+            if index.archives.is_empty() {
+                restore_chunked(&client, &args, &index).await
+            } else {
+                restore_from_archives(&client, &args, &object_prefix, &index).await
+            }
+        }
+        // No manifest, e.g. a backup made before manifests existed: fall
+        // back to discovering and extracting whatever .zip objects are
+        // under the backup's prefix.
+        Err(_) => restore_legacy_zip(&client, &args, &object_prefix).await,
+    }
+}
+
+async fn list_backups(client: &Client, bucket: &str, destination_prefix: Option<&str>) -> Result<()> {
+    let mut backups = remote_backups(client, bucket, destination_prefix).await?;
+    backups.sort_by_key(|b| std::cmp::Reverse(b.timestamp));
+
+    if backups.is_empty() {
+        println!("No backups found in gs://{bucket}");
+        return Ok(());
+    }
+
+    println!("Backups in gs://{bucket}:");
+    for backup in backups {
+        println!("  {} ({})", backup.name, backup.timestamp.format("%Y-%m-%d"));
+    }
+    Ok(())
+}
+
+fn encryption_context(mode: &EncryptionMode, args: &RestoreArgs) -> Result<Option<EncryptionContext>> {
+    match mode {
+        EncryptionMode::None => Ok(None),
+        EncryptionMode::Passphrase { salt } => {
+            let passphrase = args.passphrase.as_deref().context(
+                "This backup is passphrase-encrypted; pass --passphrase or set MUSIC_BACKUP_PASSPHRASE",
+            )?;
+            Ok(Some(EncryptionContext::from_passphrase(passphrase, salt)?))
+        }
+        EncryptionMode::Keyfile { .. } => {
+            let path = args
+                .keyfile
+                .as_deref()
+                .context("This backup is keyfile-encrypted; pass --keyfile")?;
+            Ok(Some(EncryptionContext::from_keyfile(path)?))
+        }
+    }
+}
+
+async fn restore_chunked(client: &Client, args: &RestoreArgs, index: &BackupIndex) -> Result<()> {
+    let encryption = encryption_context(&index.encryption, args)?;
+
+    let mut files_done = 0;
+    let mut chunks_done = 0;
+
+    for file in &index.files {
+        let mut contents = Vec::new();
+
+        for digest in &file.chunks {
+            let object_name = chunk_object_name(digest, &index.encryption);
+            let raw = download_object(client, &args.bucket, &object_name)
+                .await
+                .with_context(|| format!("Failed to download chunk {object_name}"))?;
+
+            let plaintext = match &encryption {
+                Some(ctx) => ctx
+                    .decrypt(&raw)
+                    .with_context(|| format!("Failed to decrypt chunk {object_name}"))?,
+                None => raw,
+            };
+
+            let mut hasher = Sha256::new();
+            hasher.update(&plaintext);
+            let actual_digest = hex::encode(hasher.finalize());
+            if &actual_digest != digest {
+                anyhow::bail!(
+                    "Chunk integrity check failed for {}: expected {digest}, got {actual_digest}",
+                    file.path.display()
+                );
+            }
+
+            chunks_done += 1;
+            contents.extend_from_slice(&plaintext);
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(&contents);
+        let whole_file_digest = hex::encode(hasher.finalize());
+        if whole_file_digest != file.digest {
+            anyhow::bail!(
+                "Reconstructed file digest mismatch for {}: expected {}, got {whole_file_digest}",
+                file.path.display(),
+                file.digest
+            );
+        }
+
+        if !args.verify {
+            let target_path = args.target.as_ref().unwrap().join(&file.path);
+            if let Some(parent) = target_path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            std::fs::write(&target_path, &contents)
+                .with_context(|| format!("Failed to write {}", target_path.display()))?;
+        }
+
+        files_done += 1;
+    }
+
+    if args.verify {
+        println!("Verified {files_done} files, {chunks_done} chunks: all chunk digests match.");
+    } else {
+        println!(
+            "Restored {files_done} files ({chunks_done} chunks) to {}",
+            args.target.as_ref().unwrap().display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Restore a backup stored as one or more zip archives, using the manifest's
+/// `archives` list to know which objects to download and `files` to verify
+/// what came out of them against the recorded size/digest.
+async fn restore_from_archives(
+    client: &Client,
+    args: &RestoreArgs,
+    object_prefix: &str,
+    index: &BackupIndex,
+) -> Result<()> {
+    let target = args.target.as_ref();
+
+    for archive_name in &index.archives {
+        let object_name = format!("{object_prefix}{archive_name}");
+        let bytes = download_object(client, &args.bucket, &object_name)
+            .await
+            .with_context(|| format!("Failed to download archive {object_name}"))?;
+
+        let reader = std::io::Cursor::new(bytes);
+        let mut archive = zip::ZipArchive::new(reader)
+            .with_context(|| format!("Failed to open {object_name}"))?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let Some(relative) = entry.enclosed_name().map(|p| p.to_path_buf()) else {
+                continue;
+            };
+            if entry.is_dir() {
+                if !args.verify {
+                    std::fs::create_dir_all(target.unwrap().join(&relative))?;
+                }
+                continue;
+            }
+
+            let mut contents = Vec::new();
+            std::io::copy(&mut entry, &mut contents)?;
+
+            if let Some(expected) = index.files.iter().find(|f| f.path == relative) {
+                let mut hasher = Sha256::new();
+                hasher.update(&contents);
+                let actual_digest = hex::encode(hasher.finalize());
+                if contents.len() as u64 != expected.size || actual_digest != expected.digest {
+                    anyhow::bail!(
+                        "Archive integrity check failed for {}: expected {} ({} bytes), got {actual_digest} ({} bytes)",
+                        relative.display(),
+                        expected.digest,
+                        expected.size,
+                        contents.len()
+                    );
+                }
+            }
+
+            if !args.verify {
+                let out_path = target.unwrap().join(&relative);
+                if let Some(parent) = out_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&out_path, &contents)
+                    .with_context(|| format!("Failed to write {}", out_path.display()))?;
+            }
+        }
+    }
+
+    if args.verify {
+        println!(
+            "Verified {} files across {} archives: all digests match.",
+            index.total_files,
+            index.archives.len()
+        );
+    } else {
+        println!(
+            "Restored {} files across {} archives to {}",
+            index.total_files,
+            index.archives.len(),
+            target.unwrap().display()
+        );
+    }
+
+    Ok(())
+}
+
+async fn restore_legacy_zip(client: &Client, args: &RestoreArgs, object_prefix: &str) -> Result<()> {
+    let objects = client
+        .list_objects(&ListObjectsRequest {
+            bucket: args.bucket.clone(),
+            prefix: Some(object_prefix.to_string()),
+            ..Default::default()
+        })
+        .await
+        .context("Failed to list objects for legacy zip backup")?;
+
+    let zip_objects: Vec<_> = objects
+        .items
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|o| o.name.ends_with(".zip"))
+        .collect();
+
+    if zip_objects.is_empty() {
+        anyhow::bail!("No index.json or .zip objects found under {object_prefix}");
+    }
+
+    for object in zip_objects {
+        let bytes = download_object(client, &args.bucket, &object.name).await?;
+        if args.verify {
+            println!("Found {} ({} bytes)", object.name, bytes.len());
+            continue;
+        }
+
+        let target = args.target.as_ref().unwrap();
+        std::fs::create_dir_all(target)
+            .with_context(|| format!("Failed to create {}", target.display()))?;
+        let reader = std::io::Cursor::new(bytes);
+        let mut archive =
+            zip::ZipArchive::new(reader).with_context(|| format!("Failed to open {}", object.name))?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let out_path = match entry.enclosed_name() {
+                Some(path) => target.join(path),
+                None => continue,
+            };
+            if entry.is_dir() {
+                std::fs::create_dir_all(&out_path)?;
+                continue;
+            }
+            if let Some(parent) = out_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut out_file = std::fs::File::create(&out_path)
+                .with_context(|| format!("Failed to create {}", out_path.display()))?;
+            std::io::copy(&mut entry, &mut out_file)?;
+        }
+    }
+
+    if args.verify {
+        println!("Verified legacy zip backup under {object_prefix}");
+    } else {
+        println!("Restored legacy zip backup to {}", args.target.as_ref().unwrap().display());
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn download_object(client: &Client, bucket: &str, object_name: &str) -> Result<Vec<u8>> {
+    client
+        .download_object(
+            &GetObjectRequest {
+                bucket: bucket.to_string(),
+                object: object_name.to_string(),
+                ..Default::default()
+            },
+            &Range::default(),
+        )
+        .await
+        .with_context(|| format!("Failed to download {object_name}"))
+}
@@ -0,0 +1,238 @@
+//! Grandfather-father-son (GFS) retention pruning.
+//!
+//! Replaces a flat "keep N days" cutoff with independent rules for how many
+//! of the most recent backups to keep unconditionally, plus how many daily,
+//! weekly, monthly and yearly snapshots to keep. The same plan is applied to
+//! both the local `tmp/` directory and the GCS bucket so remote history
+//! follows the same policy as local history.
+
+use chrono::{DateTime, Datelike, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// A single backup known to the pruning engine, identified by name (the
+/// zip/chunked-backup directory name) and the timestamp it was taken at.
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub name: String,
+    pub timestamp: DateTime<Local>,
+}
+
+/// GFS retention rule counts. A count of 0 disables that rule.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PruneOptions {
+    /// Keep this many of the most recent backups, unconditionally.
+    #[serde(default)]
+    pub keep_last: usize,
+    /// Keep the newest backup of each of this many distinct calendar days.
+    #[serde(default)]
+    pub keep_daily: usize,
+    /// Keep the newest backup of each of this many distinct ISO weeks.
+    #[serde(default)]
+    pub keep_weekly: usize,
+    /// Keep the newest backup of each of this many distinct calendar months.
+    #[serde(default)]
+    pub keep_monthly: usize,
+    /// Keep the newest backup of each of this many distinct calendar years.
+    #[serde(default)]
+    pub keep_yearly: usize,
+}
+
+/// A backup paired with the pruning engine's keep/remove decision for it.
+pub struct Decision {
+    pub backup: Backup,
+    pub keep: bool,
+}
+
+/// Decide which of `backups` to keep under `opts`.
+///
+/// Backups are sorted newest-first. `keep_last` claims the first N
+/// unconditionally; each periodic rule then buckets the *remaining*
+/// backups (those not already claimed by `keep_last`) by its time key and
+/// keeps the newest backup in each distinct bucket, up to that rule's
+/// count. A backup is removed only if no rule kept it.
+pub fn plan(backups: &[Backup], opts: &PruneOptions) -> Vec<Decision> {
+    let mut sorted: Vec<&Backup> = backups.iter().collect();
+    sorted.sort_by_key(|b| std::cmp::Reverse(b.timestamp));
+
+    let mut kept: HashSet<usize> = HashSet::new();
+
+    let keep_last = opts.keep_last.min(sorted.len());
+    for i in 0..keep_last {
+        kept.insert(i);
+    }
+
+    apply_rule(&sorted, keep_last, opts.keep_daily, &mut kept, |t| {
+        t.format("%Y-%m-%d").to_string()
+    });
+    apply_rule(&sorted, keep_last, opts.keep_weekly, &mut kept, |t| {
+        let week = t.iso_week();
+        format!("{}-{:02}", week.year(), week.week())
+    });
+    apply_rule(&sorted, keep_last, opts.keep_monthly, &mut kept, |t| {
+        t.format("%Y-%m").to_string()
+    });
+    apply_rule(&sorted, keep_last, opts.keep_yearly, &mut kept, |t| {
+        t.format("%Y").to_string()
+    });
+
+    sorted
+        .into_iter()
+        .enumerate()
+        .map(|(i, backup)| Decision {
+            backup: backup.clone(),
+            keep: kept.contains(&i),
+        })
+        .collect()
+}
+
+fn apply_rule(
+    sorted: &[&Backup],
+    keep_last: usize,
+    count: usize,
+    kept: &mut HashSet<usize>,
+    key_fn: impl Fn(DateTime<Local>) -> String,
+) {
+    if count == 0 {
+        return;
+    }
+
+    let mut seen_buckets = HashSet::new();
+    for (i, backup) in sorted.iter().enumerate() {
+        if i < keep_last {
+            continue;
+        }
+        if seen_buckets.len() >= count {
+            break;
+        }
+        let key = key_fn(backup.timestamp);
+        if seen_buckets.contains(&key) {
+            continue;
+        }
+        seen_buckets.insert(key);
+        kept.insert(i);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn backup_at(name: &str, days_ago: i64) -> Backup {
+        Backup {
+            name: name.to_string(),
+            timestamp: Local::now() - Duration::days(days_ago),
+        }
+    }
+
+    fn kept_names(backups: &[Backup], decisions: &[Decision]) -> Vec<String> {
+        let mut names: Vec<String> = decisions
+            .iter()
+            .filter(|d| d.keep)
+            .map(|d| d.backup.name.clone())
+            .collect();
+        names.sort();
+        let mut all_names: Vec<String> = backups.iter().map(|b| b.name.clone()).collect();
+        all_names.sort();
+        assert!(names.iter().all(|n| all_names.contains(n)));
+        names
+    }
+
+    #[test]
+    fn keep_last_claims_the_newest_n_unconditionally() {
+        let backups = vec![
+            backup_at("d0", 0),
+            backup_at("d1", 1),
+            backup_at("d2", 2),
+            backup_at("d3", 3),
+        ];
+        let opts = PruneOptions {
+            keep_last: 2,
+            ..Default::default()
+        };
+        let decisions = plan(&backups, &opts);
+        assert_eq!(kept_names(&backups, &decisions), vec!["d0", "d1"]);
+    }
+
+    #[test]
+    fn zero_count_rules_keep_nothing_beyond_keep_last() {
+        let backups = vec![backup_at("d0", 0), backup_at("d1", 1), backup_at("d2", 30)];
+        let opts = PruneOptions::default();
+        let decisions = plan(&backups, &opts);
+        assert!(kept_names(&backups, &decisions).is_empty());
+    }
+
+    #[test]
+    fn keep_daily_keeps_only_the_newest_buckets_up_to_its_budget() {
+        let backups = vec![backup_at("today", 0), backup_at("yesterday", 1), backup_at("two-ago", 2)];
+        let opts = PruneOptions {
+            keep_daily: 2,
+            ..Default::default()
+        };
+        let decisions = plan(&backups, &opts);
+        let mut kept = kept_names(&backups, &decisions);
+        kept.sort();
+        assert_eq!(kept, vec!["today", "yesterday"]);
+    }
+
+    #[test]
+    fn keep_daily_only_keeps_one_per_bucket_even_with_duplicates() {
+        // Force two backups into the exact same day bucket by giving them
+        // identical day-granularity ages; apply_rule must keep only the
+        // first (newest) one it sees per bucket.
+        let now = Local::now();
+        let backups = vec![
+            Backup { name: "a".to_string(), timestamp: now },
+            Backup { name: "b".to_string(), timestamp: now - Duration::seconds(1) },
+        ];
+        let opts = PruneOptions {
+            keep_daily: 5,
+            ..Default::default()
+        };
+        let decisions = plan(&backups, &opts);
+        assert_eq!(kept_names(&backups, &decisions), vec!["a"]);
+    }
+
+    #[test]
+    fn keep_last_excludes_those_backups_from_periodic_bucket_counting() {
+        // With keep_last=1, the newest backup is already kept unconditionally
+        // and must not also consume one of keep_daily's buckets.
+        let backups = vec![backup_at("d0", 0), backup_at("d1", 1), backup_at("d2", 2)];
+        let opts = PruneOptions {
+            keep_last: 1,
+            keep_daily: 1,
+            ..Default::default()
+        };
+        let decisions = plan(&backups, &opts);
+        let mut kept = kept_names(&backups, &decisions);
+        kept.sort();
+        assert_eq!(kept, vec!["d0", "d1"]);
+    }
+
+    #[test]
+    fn keep_weekly_buckets_by_iso_week() {
+        let backups = vec![backup_at("w0", 0), backup_at("w0-again", 1), backup_at("w-ago", 10)];
+        let opts = PruneOptions {
+            keep_weekly: 2,
+            ..Default::default()
+        };
+        let decisions = plan(&backups, &opts);
+        let kept = kept_names(&backups, &decisions);
+        // Exactly one backup survives per distinct ISO week covered, up to
+        // the keep_weekly budget of 2 weeks.
+        assert!(kept.len() <= 2);
+        assert!(!kept.is_empty());
+    }
+
+    #[test]
+    fn no_backups_removed_when_every_rule_is_satisfied() {
+        let backups = vec![backup_at("only", 0)];
+        let opts = PruneOptions {
+            keep_last: 1,
+            ..Default::default()
+        };
+        let decisions = plan(&backups, &opts);
+        assert!(decisions.iter().all(|d| d.keep));
+    }
+}
@@ -0,0 +1,83 @@
+//! Bounded-memory file streaming for uploads.
+//!
+//! Reads a file in fixed-size buffers pushed through a bounded
+//! `tokio::sync::mpsc` channel, the same shape Proxmox's async client uses
+//! to keep upload memory flat regardless of archive size: at most
+//! `CHANNEL_CAPACITY` buffers wait in the channel at once (plus the one being
+//! read and the one being uploaded), independent of how large the file on
+//! disk is.
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Size of each buffer read off disk and pushed through the upload channel.
+/// Also doubles as the GCS resumable-upload chunk size, since it's already a
+/// multiple of the 256 KiB the protocol requires for every non-final chunk.
+pub const BUFFER_SIZE: usize = 8 * 1024 * 1024;
+
+/// Number of in-flight buffers the channel holds before the reader task
+/// blocks, bounding memory use to roughly `CHANNEL_CAPACITY * BUFFER_SIZE`.
+const CHANNEL_CAPACITY: usize = 4;
+
+/// Stream `path` starting at `offset`, so a resumed upload only re-reads the
+/// bytes the server hasn't committed yet. Returns the stream alongside the
+/// file's total size so callers can report progress and size the upload.
+pub async fn stream_file(path: &Path, offset: u64) -> Result<(ReceiverStream<std::io::Result<Bytes>>, u64)> {
+    let total_len = tokio::fs::metadata(path)
+        .await
+        .with_context(|| format!("Failed to stat {}", path.display()))?
+        .len();
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let owned_path = path.to_path_buf();
+
+    tokio::spawn(async move {
+        if let Err(err) = read_into_channel(&owned_path, offset, &tx).await {
+            let _ = tx.send(Err(err)).await;
+        }
+    });
+
+    Ok((ReceiverStream::new(rx), total_len))
+}
+
+async fn read_into_channel(
+    path: &Path,
+    offset: u64,
+    tx: &mpsc::Sender<std::io::Result<Bytes>>,
+) -> std::io::Result<()> {
+    let mut file = File::open(path).await?;
+    if offset > 0 {
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+    }
+
+    let mut buf = vec![0u8; BUFFER_SIZE];
+    loop {
+        // A single read may return fewer bytes than asked for; keep reading
+        // until the buffer is full or the file ends, so every buffer but the
+        // last is exactly BUFFER_SIZE.
+        let mut filled = 0;
+        while filled < BUFFER_SIZE {
+            let n = file.read(&mut buf[filled..]).await?;
+            if n == 0 {
+                break;
+            }
+            filled += n;
+        }
+        if filled == 0 {
+            return Ok(());
+        }
+        let chunk = Bytes::copy_from_slice(&buf[..filled]);
+        if tx.send(Ok(chunk)).await.is_err() {
+            // Receiver dropped; nothing left to do.
+            return Ok(());
+        }
+        if filled < BUFFER_SIZE {
+            return Ok(());
+        }
+    }
+}